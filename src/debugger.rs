@@ -0,0 +1,320 @@
+// src/debugger.rs
+
+use std::collections::HashSet;
+use std::io::{self, BufRead, Write};
+
+use crate::cpu::{self, CPU};
+use crate::error::VmError;
+use crate::io::IODevice;
+
+/// A step/breakpoint debugger that wraps the CPU's fetch-decode-execute cycle.
+///
+/// Drives the CPU one instruction at a time and supports breakpoints on
+/// program-counter addresses, single-stepping, continuing until a
+/// breakpoint, register/memory dumps, and a trace mode that prints each
+/// decoded instruction as it executes.
+pub struct Debugger {
+    breakpoints: HashSet<usize>,
+    trace: bool,
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Debugger {
+            breakpoints: HashSet::new(),
+            trace: false,
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, address: usize) {
+        self.breakpoints.insert(address);
+    }
+
+    pub fn remove_breakpoint(&mut self, address: usize) {
+        self.breakpoints.remove(&address);
+    }
+
+    pub fn set_trace(&mut self, enabled: bool) {
+        self.trace = enabled;
+    }
+
+    /// Executes exactly one instruction, printing it first if trace mode is on.
+    ///
+    /// Also services pending interrupts afterward, the same as `CPU::run()`'s
+    /// loop, so stepping under the debugger doesn't diverge from a plain run.
+    pub fn step<D: IODevice>(&self, cpu: &mut CPU<D>) -> Result<(), VmError> {
+        let info = cpu.step()?;
+        if self.trace {
+            println!(
+                "{:04X}: {} R{}, {}",
+                info.pc,
+                cpu::mnemonic(info.opcode),
+                info.r1,
+                info.operand
+            );
+        }
+        cpu.service_interrupts()
+    }
+
+    /// Runs until a breakpoint is hit or the CPU halts.
+    pub fn continue_execution<D: IODevice>(&self, cpu: &mut CPU<D>) -> Result<(), VmError> {
+        while !cpu.is_halted() {
+            self.step(cpu)?;
+            if self.breakpoints.contains(&cpu.program_counter()) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads commands from stdin and drives `cpu` until the user quits or the CPU halts.
+    ///
+    /// Supports `step [count]`, `continue`, `break <addr>`, `delete <addr>`,
+    /// `regs`, `mem <addr> <len>`, `trace`, and `quit`. An empty line repeats
+    /// the last command.
+    pub fn run<D: IODevice>(&mut self, cpu: &mut CPU<D>) {
+        // A `BufReader` over the unlocked handle, not a persisted `StdinLock`:
+        // locking for the whole session would deadlock against any `IODevice`
+        // (e.g. `ConsoleDevice`) that reads its own `io::stdin()` during `INPUT`.
+        self.run_with_input(cpu, &mut io::BufReader::new(io::stdin()))
+    }
+
+    /// The body of `run`, generalized over the input source so it can be
+    /// driven by a scripted `Cursor` in tests instead of real stdin.
+    fn run_with_input<D: IODevice, R: BufRead>(&mut self, cpu: &mut CPU<D>, input: &mut R) {
+        let mut last_command = String::new();
+        while !cpu.is_halted() {
+            print!("(dbg) ");
+            if io::stdout().flush().is_err() {
+                return;
+            }
+
+            let mut line = String::new();
+            if input.read_line(&mut line).unwrap_or(0) == 0 {
+                return;
+            }
+
+            let command = match line.trim() {
+                "" => last_command.clone(),
+                other => other.to_string(),
+            };
+            if command.is_empty() {
+                continue;
+            }
+            last_command = command.clone();
+
+            if !self.execute_command(cpu, &command) {
+                return;
+            }
+        }
+    }
+
+    fn execute_command<D: IODevice>(&mut self, cpu: &mut CPU<D>, command: &str) -> bool {
+        let mut parts = command.split_whitespace();
+        match parts.next() {
+            Some("step") => {
+                let count: usize = parts.next().and_then(|n| n.parse().ok()).unwrap_or(1);
+                for _ in 0..count {
+                    if cpu.is_halted() {
+                        break;
+                    }
+                    if let Err(err) = self.step(cpu) {
+                        println!("error: {}", err);
+                        break;
+                    }
+                }
+            }
+            Some("continue") => {
+                if let Err(err) = self.continue_execution(cpu) {
+                    println!("error: {}", err);
+                }
+            }
+            Some("break") => match parts.next().and_then(parse_addr) {
+                Some(addr) => {
+                    self.add_breakpoint(addr);
+                    println!("breakpoint set at {:#06X}", addr);
+                }
+                None => println!("usage: break <addr>"),
+            },
+            Some("delete") => match parts.next().and_then(parse_addr) {
+                Some(addr) => {
+                    self.remove_breakpoint(addr);
+                    println!("breakpoint removed at {:#06X}", addr);
+                }
+                None => println!("usage: delete <addr>"),
+            },
+            Some("regs") => self.print_registers(cpu),
+            Some("mem") => {
+                let addr = parts.next().and_then(parse_addr);
+                let len = parts.next().and_then(|n| n.parse().ok());
+                match (addr, len) {
+                    (Some(addr), Some(len)) => self.dump_memory(cpu, addr, len),
+                    _ => println!("usage: mem <addr> <len>"),
+                }
+            }
+            Some("trace") => {
+                self.trace = !self.trace;
+                println!("trace {}", if self.trace { "on" } else { "off" });
+            }
+            Some("quit") => return false,
+            _ => println!("unknown command: {}", command),
+        }
+        true
+    }
+
+    fn print_registers<D: IODevice>(&self, cpu: &CPU<D>) {
+        for (i, reg) in cpu.registers().iter().enumerate() {
+            println!("R{}: {:08X}", i, reg);
+        }
+        println!("Flags: {:08b}", cpu.flags());
+        println!("PC: {:#06X}", cpu.program_counter());
+        println!("SP: {:#06X}", cpu.sp());
+    }
+
+    fn dump_memory<D: IODevice>(&self, cpu: &mut CPU<D>, addr: usize, len: usize) {
+        match cpu.read_memory(addr, len) {
+            Ok(bytes) => {
+                for (row, chunk) in bytes.chunks(16).enumerate() {
+                    let line: Vec<String> = chunk.iter().map(|b| format!("{:02X}", b)).collect();
+                    println!("{:#06X}: {}", addr + row * 16, line.join(" "));
+                }
+            }
+            Err(err) => println!("error: {}", err),
+        }
+    }
+}
+
+fn parse_addr(text: &str) -> Option<usize> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::MockIOController;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_parse_addr_hex_and_decimal() {
+        assert_eq!(parse_addr("0x10"), Some(0x10));
+        assert_eq!(parse_addr("0X1A"), Some(0x1A));
+        assert_eq!(parse_addr("42"), Some(42));
+        assert_eq!(parse_addr("not an address"), None);
+    }
+
+    /// Three `LOADI R0, n` instructions followed by `HALT`, at addresses
+    /// 0, 6, 12, and 18 respectively. Used by the tests below to exercise
+    /// breakpoints and stepping against known addresses.
+    fn three_loadi_program() -> CPU<MockIOController> {
+        let io_controller = MockIOController::new();
+        let mmu = crate::memory::MemoryManagementUnit::new();
+        let mut cpu = CPU::new(io_controller, mmu);
+        let program = vec![
+            0x54, 0x00, 0x01, 0x00, 0x00, 0x00, // 0x00: LOADI R0, 1
+            0x54, 0x00, 0x02, 0x00, 0x00, 0x00, // 0x06: LOADI R0, 2
+            0x54, 0x00, 0x03, 0x00, 0x00, 0x00, // 0x0C: LOADI R0, 3
+            0xFF, 0x00, 0x00, // 0x12: HALT
+        ];
+        cpu.load_program(&program);
+        cpu
+    }
+
+    #[test]
+    fn test_breakpoint_hit_with_hex_address() {
+        let mut cpu = three_loadi_program();
+        let mut debugger = Debugger::new();
+
+        assert!(debugger.execute_command(&mut cpu, "break 0x6"));
+        assert!(debugger.execute_command(&mut cpu, "continue"));
+
+        assert_eq!(cpu.program_counter(), 6);
+        assert_eq!(cpu.registers()[0], 1);
+        assert!(!cpu.is_halted());
+    }
+
+    #[test]
+    fn test_breakpoint_hit_with_decimal_address() {
+        let mut cpu = three_loadi_program();
+        let mut debugger = Debugger::new();
+
+        assert!(debugger.execute_command(&mut cpu, "break 12"));
+        assert!(debugger.execute_command(&mut cpu, "continue"));
+
+        assert_eq!(cpu.program_counter(), 12);
+        assert_eq!(cpu.registers()[0], 2);
+    }
+
+    #[test]
+    fn test_delete_removes_a_breakpoint() {
+        let mut cpu = three_loadi_program();
+        let mut debugger = Debugger::new();
+
+        assert!(debugger.execute_command(&mut cpu, "break 0x6"));
+        assert!(debugger.execute_command(&mut cpu, "delete 0x6"));
+        assert!(debugger.execute_command(&mut cpu, "continue"));
+
+        // With the breakpoint removed, continue should run to completion.
+        assert!(cpu.is_halted());
+    }
+
+    #[test]
+    fn test_step_with_count_executes_that_many_instructions() {
+        let mut cpu = three_loadi_program();
+        let mut debugger = Debugger::new();
+
+        assert!(debugger.execute_command(&mut cpu, "step 3"));
+
+        assert_eq!(cpu.program_counter(), 18);
+        assert_eq!(cpu.registers()[0], 3);
+        assert!(!cpu.is_halted());
+    }
+
+    #[test]
+    fn test_step_with_no_count_defaults_to_one() {
+        let mut cpu = three_loadi_program();
+        let mut debugger = Debugger::new();
+
+        assert!(debugger.execute_command(&mut cpu, "step"));
+
+        assert_eq!(cpu.program_counter(), 6);
+        assert_eq!(cpu.registers()[0], 1);
+    }
+
+    #[test]
+    fn test_empty_line_repeats_last_command() {
+        let mut cpu = three_loadi_program();
+        let mut debugger = Debugger::new();
+        let mut input = Cursor::new(b"step 1\n\nquit\n".to_vec());
+
+        debugger.run_with_input(&mut cpu, &mut input);
+
+        // "step 1" ran once, the empty line repeated it, then "quit" stopped.
+        assert_eq!(cpu.program_counter(), 12);
+        assert_eq!(cpu.registers()[0], 2);
+    }
+
+    #[test]
+    fn test_trace_command_toggles_trace_mode() {
+        let mut cpu = three_loadi_program();
+        let mut debugger = Debugger::new();
+        assert!(!debugger.trace);
+
+        assert!(debugger.execute_command(&mut cpu, "trace"));
+        assert!(debugger.trace);
+
+        assert!(debugger.execute_command(&mut cpu, "trace"));
+        assert!(!debugger.trace);
+    }
+
+    #[test]
+    fn test_mem_command_parses_hex_and_decimal_address() {
+        let mut cpu = three_loadi_program();
+        let mut debugger = Debugger::new();
+
+        assert!(debugger.execute_command(&mut cpu, "mem 0x0 4"));
+        assert!(debugger.execute_command(&mut cpu, "mem 0 4"));
+    }
+}
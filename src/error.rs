@@ -0,0 +1,31 @@
+// src/error.rs
+
+use std::fmt;
+
+/// Errors that can occur while executing an EtherVM program.
+///
+/// Every instruction handler and MMU accessor returns one of these instead
+/// of panicking, so a host embedding the VM can recover instead of having
+/// the whole process aborted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VmError {
+    DivisionByZero,
+    UnknownOpcode(u8),
+    MemoryOutOfBounds(usize),
+    InvalidRegister(u8),
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VmError::DivisionByZero => write!(f, "division by zero"),
+            VmError::UnknownOpcode(opcode) => write!(f, "unknown opcode: {:#04X}", opcode),
+            VmError::MemoryOutOfBounds(address) => {
+                write!(f, "memory access out of bounds at address {:#06X}", address)
+            }
+            VmError::InvalidRegister(reg) => write!(f, "invalid register index: {}", reg),
+        }
+    }
+}
+
+impl std::error::Error for VmError {}
@@ -0,0 +1,113 @@
+// src/interrupt.rs
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::io::IODevice;
+
+/// Number of interrupt lines the controller can track; line `n` corresponds
+/// to bit `n` of the pending and mask registers.
+const LINE_COUNT: u32 = 8;
+
+/// A small GIC-style interrupt controller: devices raise a line, and the CPU
+/// polls for the lowest-numbered pending, unmasked line between instructions.
+pub struct InterruptController {
+    pending: u8,
+    mask: u8,
+    enabled: bool,
+}
+
+impl InterruptController {
+    pub fn new() -> Self {
+        InterruptController {
+            pending: 0,
+            mask: 0,
+            enabled: false,
+        }
+    }
+
+    /// Marks `line` as pending. Called by devices to signal the CPU asynchronously.
+    pub fn raise_irq(&mut self, line: u8) {
+        if (line as u32) < LINE_COUNT {
+            self.pending |= 1 << line;
+        }
+    }
+
+    /// Masks (1) or unmasks (0) lines; a masked line is never reported as pending.
+    pub fn set_mask(&mut self, mask: u8) {
+        self.mask = mask;
+    }
+
+    pub fn enable(&mut self) {
+        self.enabled = true;
+    }
+
+    pub fn disable(&mut self) {
+        self.enabled = false;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Returns and clears the lowest-numbered pending, unmasked line, if any.
+    pub fn take_pending(&mut self) -> Option<u8> {
+        let active = self.pending & !self.mask;
+        if active == 0 {
+            return None;
+        }
+        let line = active.trailing_zeros() as u8;
+        self.pending &= !(1 << line);
+        Some(line)
+    }
+}
+
+/// A memory-mapped device that raises `line` whenever it's written to,
+/// sharing the CPU's controller via `CPU::interrupt_controller()`. This is
+/// how a real device signals the CPU asynchronously through the bus, rather
+/// than the CPU polling the device directly.
+pub struct InterruptingDevice {
+    interrupts: Rc<RefCell<InterruptController>>,
+    line: u8,
+}
+
+impl InterruptingDevice {
+    pub fn new(interrupts: Rc<RefCell<InterruptController>>, line: u8) -> Self {
+        InterruptingDevice { interrupts, line }
+    }
+}
+
+impl IODevice for InterruptingDevice {
+    fn input(&mut self) -> u32 {
+        0
+    }
+
+    fn output(&mut self, _value: u32) {
+        self.interrupts.borrow_mut().raise_irq(self.line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_pending_picks_lowest_unmasked_line() {
+        let mut controller = InterruptController::new();
+        controller.raise_irq(3);
+        controller.raise_irq(1);
+
+        assert_eq!(controller.take_pending(), Some(1));
+        assert_eq!(controller.take_pending(), Some(3));
+        assert_eq!(controller.take_pending(), None);
+    }
+
+    #[test]
+    fn test_masked_line_is_not_reported() {
+        let mut controller = InterruptController::new();
+        controller.set_mask(0b0000_0010);
+        controller.raise_irq(1);
+
+        assert_eq!(controller.take_pending(), None);
+    }
+}
@@ -17,16 +17,25 @@ impl IOController {
             devices: vec![Box::new(ConsoleDevice {})],
         }
     }
+}
 
-    pub fn input(&mut self) -> u32 {
+impl IODevice for IOController {
+    fn input(&mut self) -> u32 {
         self.devices[0].input()
     }
 
-    pub fn output(&mut self, value: u32) {
+    fn output(&mut self, value: u32) {
         self.devices[0].output(value);
     }
 }
 
+/// A boxed console device ready to be mapped onto the MMU's device bus (see
+/// `MemoryManagementUnit::map_device`), for production code that wants
+/// memory-mapped console I/O instead of going through `IOController`.
+pub fn console_device() -> Box<dyn IODevice> {
+    Box::new(ConsoleDevice {})
+}
+
 struct ConsoleDevice {}
 
 impl IODevice for ConsoleDevice {
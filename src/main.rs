@@ -1,29 +1,78 @@
 // src/main.rs
 
 mod cpu;
+mod debugger;
+mod error;
+mod interrupt;
 mod io;
 mod memory;
 
 use cpu::CPU;
+use debugger::Debugger;
+use interrupt::InterruptingDevice;
 use io::IOController;
 use memory::MemoryManagementUnit;
 
+/// Address of the bus-mapped console device's word window (see
+/// `io::console_device`), separate from `IOController`'s register-based
+/// INPUT/OUTPUT channel.
+const CONSOLE_ADDR: usize = 0xF000;
+
+/// Address of the bus-mapped device that raises interrupt line 0 whenever
+/// it's written to.
+const IRQ_ADDR: usize = 0xF010;
+
+/// Address of the interrupt handler, written into the vector table before
+/// the CPU starts.
+const HANDLER_ADDR: u32 = 0x40;
+
 fn main() {
     println!("Virtual Machine Initializing...");
+    let debug_mode = std::env::args().any(|arg| arg == "--debug");
+
     let io_controller = IOController::new();
-    let mmu = MemoryManagementUnit::new();
+    let mut mmu = MemoryManagementUnit::new();
+    mmu.write_word(cpu::INTERRUPT_VECTOR_BASE, HANDLER_ADDR)
+        .expect("vector table address is in bounds");
+
     let mut cpu = CPU::new(io_controller, mmu);
 
-    // Load a simple program into memory
-    let program = vec![
-        0x46, 0x00, // INPUT R0
-        0x47, 0x01, // OUTPUT R1
-        0x40, 0x10, // ADD R1, R0
-        0x47, 0x01, // OUTPUT R1
-        0x4E, 0x00, // CMP R0, R0
-        0x50, 0x00, // JE 0 (Loop back to start)
+    // Map a console device and an interrupt-raising device onto the address
+    // bus, so `map_device` is exercised by the real program rather than only
+    // by its own unit test.
+    cpu.map_device(CONSOLE_ADDR..CONSOLE_ADDR + 4, io::console_device());
+    let irq_device = InterruptingDevice::new(cpu.interrupt_controller(), 0);
+    cpu.map_device(IRQ_ADDR..IRQ_ADDR + 4, Box::new(irq_device));
+    // Line 0 is the only line this demo uses; make sure it isn't masked.
+    cpu.interrupt_controller().borrow_mut().set_mask(0);
+
+    // Reads a number, echoes it through the bus-mapped console, then writes
+    // it to the bus-mapped IRQ device to raise interrupt line 0. The CPU
+    // busy-waits until the interrupt is serviced and the handler halts it.
+    let mut program = vec![
+        0x54, 0x01, 0x00, 0xF0, 0x00, 0x00, // LOADI R1, CONSOLE_ADDR
+        0x54, 0x02, 0x10, 0xF0, 0x00, 0x00, // LOADI R2, IRQ_ADDR
+        0x54, 0x03, 0x1E, 0x00, 0x00, 0x00, // LOADI R3, 0x1E (loop target, see below)
+        0x5C, 0x00, 0x00, // STI
+        0x46, 0x00, 0x00, // INPUT R0
+        0x67, 0x00, 0x01, // SW R0, [R1]  (echo through the bus console)
+        0x67, 0x00, 0x02, // SW R0, [R2]  (raise interrupt line 0)
+        0x4F, 0x03, 0x00, // 0x1E: JMP R3 (busy-wait for the interrupt)
     ];
+    program.resize(HANDLER_ADDR as usize, 0x00);
+    program.extend(vec![0xFF, 0x00, 0x00]); // handler: HALT
 
     cpu.load_program(&program);
-    cpu.run();
+
+    let result = if debug_mode {
+        let mut debugger = Debugger::new();
+        debugger.set_trace(true);
+        cpu.run_with_debugger(&mut debugger);
+        Ok(())
+    } else {
+        cpu.run()
+    };
+    if let Err(err) = result {
+        eprintln!("VM halted due to error: {}", err);
+    }
 }
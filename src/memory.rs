@@ -1,32 +1,158 @@
 // src/memory.rs
 
+use std::ops::Range;
+
+use crate::error::VmError;
+use crate::io::IODevice;
+
+/// A memory-mapped device window: an address range that dispatches to a
+/// device's `input`/`output` instead of being backed by RAM.
+struct DeviceWindow {
+    range: Range<usize>,
+    device: Box<dyn IODevice>,
+}
+
+/// The width of a sized memory access.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Size {
+    Byte,
+    Half,
+    Word,
+}
+
+impl Size {
+    fn len(self) -> usize {
+        match self {
+            Size::Byte => 1,
+            Size::Half => 2,
+            Size::Word => 4,
+        }
+    }
+}
+
+/// A value read from or written to memory at a particular [`Size`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Store {
+    Byte(u8),
+    Half(u16),
+    Word(u32),
+}
+
+impl Store {
+    /// Zero-extends the stored value to 32 bits, for loading into a register.
+    pub fn zero_extend(self) -> u32 {
+        match self {
+            Store::Byte(value) => value as u32,
+            Store::Half(value) => value as u32,
+            Store::Word(value) => value,
+        }
+    }
+}
+
 pub struct MemoryManagementUnit {
     memory: Vec<u8>,
+    devices: Vec<DeviceWindow>,
 }
 
 impl MemoryManagementUnit {
     pub fn new() -> Self {
         MemoryManagementUnit {
             memory: vec![0; 65536], // 64KB of memory
+            devices: Vec::new(),
+        }
+    }
+
+    /// Maps `device` onto `range`. Any read or write whose address falls
+    /// inside the range is routed to the device instead of RAM.
+    pub fn map_device(&mut self, range: Range<usize>, device: Box<dyn IODevice>) {
+        self.devices.push(DeviceWindow { range, device });
+    }
+
+    fn device_at_mut(&mut self, address: usize) -> Option<&mut Box<dyn IODevice>> {
+        self.devices
+            .iter_mut()
+            .find(|window| window.range.contains(&address))
+            .map(|window| &mut window.device)
+    }
+
+    /// Reads a value of `size` starting at `address`, little-endian,
+    /// validating the full span against the backing memory.
+    pub fn read(&mut self, address: usize, size: Size) -> Result<Store, VmError> {
+        if let Some(device) = self.device_at_mut(address) {
+            let value = device.input();
+            return Ok(match size {
+                Size::Byte => Store::Byte(value as u8),
+                Size::Half => Store::Half(value as u16),
+                Size::Word => Store::Word(value),
+            });
         }
+        let end = address
+            .checked_add(size.len())
+            .ok_or(VmError::MemoryOutOfBounds(address))?;
+        let bytes = self
+            .memory
+            .get(address..end)
+            .ok_or(VmError::MemoryOutOfBounds(address))?;
+        Ok(match size {
+            Size::Byte => Store::Byte(bytes[0]),
+            Size::Half => Store::Half(u16::from_le_bytes([bytes[0], bytes[1]])),
+            Size::Word => Store::Word(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+        })
     }
 
-    pub fn read_byte(&self, address: usize) -> u8 {
-        self.memory[address]
+    /// Writes a sized value starting at `address`, little-endian, validating
+    /// the full span against the backing memory.
+    pub fn write(&mut self, address: usize, value: Store) -> Result<(), VmError> {
+        if let Some(device) = self.device_at_mut(address) {
+            device.output(value.zero_extend());
+            return Ok(());
+        }
+        let mut buf = [0u8; 4];
+        let len = match value {
+            Store::Byte(b) => {
+                buf[0] = b;
+                1
+            }
+            Store::Half(h) => {
+                buf[..2].copy_from_slice(&h.to_le_bytes());
+                2
+            }
+            Store::Word(w) => {
+                buf[..4].copy_from_slice(&w.to_le_bytes());
+                4
+            }
+        };
+        let end = address
+            .checked_add(len)
+            .ok_or(VmError::MemoryOutOfBounds(address))?;
+        let slice = self
+            .memory
+            .get_mut(address..end)
+            .ok_or(VmError::MemoryOutOfBounds(address))?;
+        slice.copy_from_slice(&buf[..len]);
+        Ok(())
     }
 
-    pub fn write_byte(&mut self, address: usize, value: u8) {
-        self.memory[address] = value;
+    pub fn read_byte(&mut self, address: usize) -> Result<u8, VmError> {
+        match self.read(address, Size::Byte)? {
+            Store::Byte(value) => Ok(value),
+            _ => unreachable!("read(Size::Byte) always returns Store::Byte"),
+        }
     }
 
-    pub fn read_word(&self, address: usize) -> u32 {
-        let bytes = &self.memory[address..address + 4];
-        u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+    pub fn write_byte(&mut self, address: usize, value: u8) -> Result<(), VmError> {
+        self.write(address, Store::Byte(value))
     }
 
-    pub fn write_word(&mut self, address: usize, value: u32) {
-        let bytes = value.to_le_bytes();
-        self.memory[address..address + 4].copy_from_slice(&bytes);
+    pub fn read_word(&mut self, address: usize) -> Result<u32, VmError> {
+        match self.read(address, Size::Word)? {
+            Store::Word(value) => Ok(value),
+            _ => unreachable!("read(Size::Word) always returns Store::Word"),
+        }
+    }
+
+    pub fn write_word(&mut self, address: usize, value: u32) -> Result<(), VmError> {
+        self.write(address, Store::Word(value))
     }
 }
 
@@ -37,25 +163,84 @@ mod tests {
     #[test]
     fn test_read_write_byte() {
         let mut mmu = MemoryManagementUnit::new();
-        mmu.write_byte(0, 42);
-        assert_eq!(mmu.read_byte(0), 42);
+        mmu.write_byte(0, 42).unwrap();
+        assert_eq!(mmu.read_byte(0).unwrap(), 42);
     }
 
     #[test]
     fn test_read_write_word() {
         let mut mmu = MemoryManagementUnit::new();
-        mmu.write_word(0, 0x12345678);
-        assert_eq!(mmu.read_word(0), 0x12345678);
+        mmu.write_word(0, 0x12345678).unwrap();
+        assert_eq!(mmu.read_word(0).unwrap(), 0x12345678);
     }
 
     #[test]
     fn test_memory_persistence() {
         let mut mmu = MemoryManagementUnit::new();
-        mmu.write_byte(100, 1);
-        mmu.write_byte(101, 2);
-        mmu.write_byte(102, 3);
-        mmu.write_byte(103, 4);
+        mmu.write_byte(100, 1).unwrap();
+        mmu.write_byte(101, 2).unwrap();
+        mmu.write_byte(102, 3).unwrap();
+        mmu.write_byte(103, 4).unwrap();
+
+        assert_eq!(mmu.read_word(100).unwrap(), 0x04030201);
+    }
+
+    #[test]
+    fn test_read_byte_out_of_bounds() {
+        let mut mmu = MemoryManagementUnit::new();
+        assert_eq!(mmu.read_byte(65536), Err(VmError::MemoryOutOfBounds(65536)));
+    }
+
+    #[test]
+    fn test_read_word_near_boundary() {
+        let mut mmu = MemoryManagementUnit::new();
+        assert_eq!(
+            mmu.read_word(65534),
+            Err(VmError::MemoryOutOfBounds(65534))
+        );
+    }
+
+    #[test]
+    fn test_sized_half_round_trip() {
+        let mut mmu = MemoryManagementUnit::new();
+        mmu.write(10, Store::Half(0xBEEF)).unwrap();
+        assert_eq!(mmu.read(10, Size::Half).unwrap(), Store::Half(0xBEEF));
+        // A half write touches only two bytes; the byte after it stays zero.
+        assert_eq!(mmu.read_byte(12).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_half_near_boundary_out_of_bounds() {
+        let mut mmu = MemoryManagementUnit::new();
+        assert_eq!(
+            mmu.write(65535, Store::Half(1)),
+            Err(VmError::MemoryOutOfBounds(65535))
+        );
+    }
+
+    struct EchoDevice {
+        last_written: u32,
+    }
+
+    impl IODevice for EchoDevice {
+        fn input(&mut self) -> u32 {
+            self.last_written
+        }
+
+        fn output(&mut self, value: u32) {
+            self.last_written = value;
+        }
+    }
+
+    #[test]
+    fn test_mapped_device_intercepts_reads_and_writes() {
+        let mut mmu = MemoryManagementUnit::new();
+        mmu.map_device(0xF000..0xF004, Box::new(EchoDevice { last_written: 0 }));
+
+        mmu.write_word(0xF000, 0xCAFEBABE).unwrap();
+        assert_eq!(mmu.read_word(0xF000).unwrap(), 0xCAFEBABE);
 
-        assert_eq!(mmu.read_word(100), 0x04030201);
+        // Writes inside the window never touch backing RAM.
+        assert_eq!(mmu.memory[0xF000..0xF004], [0, 0, 0, 0]);
     }
 }